@@ -1,18 +1,26 @@
 use crate::acme_proto::account::AccountManager;
-use crate::acme_proto::jws::encode_kid;
+use crate::acme_proto::jws::{encode_jwk, encode_kid};
 use crate::acme_proto::structs::{
     Authorization, AuthorizationStatus, NewOrder, Order, OrderStatus,
 };
 use crate::certificate::Certificate;
 use crate::error::Error;
 use crate::storage;
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
 use log::info;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 use std::fmt;
 
 mod account;
 mod certificate;
 mod http;
 pub mod jws;
+#[cfg(feature = "http-01-responder")]
+mod responder;
+mod retry;
 pub mod structs;
 
 #[derive(Clone, Debug, PartialEq)]
@@ -55,6 +63,99 @@ impl PartialEq<structs::Challenge> for Challenge {
     }
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum RevocationReason {
+    Unspecified,
+    KeyCompromise,
+    AffiliationChanged,
+    Superseded,
+    CessationOfOperation,
+}
+
+impl RevocationReason {
+    pub fn from_str(s: &str) -> Result<Self, Error> {
+        match s.to_lowercase().as_str() {
+            "unspecified" => Ok(RevocationReason::Unspecified),
+            "key-compromise" => Ok(RevocationReason::KeyCompromise),
+            "affiliation-changed" => Ok(RevocationReason::AffiliationChanged),
+            "superseded" => Ok(RevocationReason::Superseded),
+            "cessation-of-operation" => Ok(RevocationReason::CessationOfOperation),
+            _ => Err(format!("{}: unknown revocation reason.", s).into()),
+        }
+    }
+
+    /// The RFC 8555 numeric reason code sent in the `revokeCert` payload.
+    pub fn code(&self) -> u8 {
+        match self {
+            RevocationReason::Unspecified => 0,
+            RevocationReason::KeyCompromise => 1,
+            RevocationReason::AffiliationChanged => 3,
+            RevocationReason::Superseded => 4,
+            RevocationReason::CessationOfOperation => 5,
+        }
+    }
+}
+
+impl fmt::Display for RevocationReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            RevocationReason::Unspecified => "unspecified",
+            RevocationReason::KeyCompromise => "key-compromise",
+            RevocationReason::AffiliationChanged => "affiliation-changed",
+            RevocationReason::Superseded => "superseded",
+            RevocationReason::CessationOfOperation => "cessation-of-operation",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Body of a `revokeCert` request (RFC 8555 §7.6).
+#[derive(Serialize)]
+struct RevokeCert {
+    certificate: String,
+    reason: u8,
+}
+
+/// Payload of the inner JWS of a `keyChange` request (RFC 8555 §7.3.5).
+#[derive(Serialize)]
+struct KeyChange {
+    account: String,
+    #[serde(rename = "oldKey")]
+    old_key: serde_json::Value,
+}
+
+/// The renewal window a CA suggests for a certificate, as returned by the ARI
+/// `renewalInfo` resource (RFC 9773).
+#[derive(Clone, Debug, Deserialize)]
+pub struct RenewalInfo {
+    #[serde(rename = "suggestedWindow")]
+    pub suggested_window: SuggestedWindow,
+    #[serde(rename = "explanationURL")]
+    pub explanation_url: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct SuggestedWindow {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl RenewalInfo {
+    /// Pick a randomized renewal instant inside `[start, end)`, as recommended
+    /// by the ARI spec to spread load across a CA's clients.
+    pub fn renewal_time(&self) -> DateTime<Utc> {
+        let window = (self.suggested_window.end - self.suggested_window.start)
+            .num_seconds()
+            .max(0);
+        let offset = if window == 0 {
+            0
+        } else {
+            rand::thread_rng().gen_range(0..window)
+        };
+        self.suggested_window.start + Duration::seconds(offset)
+    }
+}
+
 macro_rules! set_data_builder {
     ($account: ident, $data: expr, $url: expr) => {
         |n: &str| encode_kid(&$account.priv_key, &$account.account_url, $data, &$url, n)
@@ -76,55 +177,83 @@ pub fn request_certificate(cert: &Certificate) -> Result<(), Error> {
     // 3. Get or create the account
     let (account, nonce) = AccountManager::new(cert, &directory, &nonce)?;
 
-    // 4. Create a new order
-    let new_order = NewOrder::new(&cert.domains);
+    // 4. Create a new order. When renewing a certificate we already hold, set
+    //    the `replaces` field to the predecessor's ARI certificate identifier
+    //    so the CA can correlate the renewal.
+    let mut new_order = NewOrder::new(&cert.domains);
+    new_order.replaces = ari_cert_id(cert).ok();
     let new_order = serde_json::to_string(&new_order)?;
     let data_builder = set_data_builder!(account, new_order.as_bytes(), directory.new_order);
     let (order, order_url, mut nonce): (Order, String, String) =
         http::get_obj_loc(&directory.new_order, &data_builder, &nonce)?;
 
-    // 5. Get all the required authorizations
-    for auth_url in order.authorizations.iter() {
-        let data_builder = set_empty_data_builder!(account, auth_url);
-        let (auth, new_nonce): (Authorization, String) =
-            http::get_obj(&auth_url, &data_builder, &nonce)?;
-        nonce = new_nonce;
+    // Handle every authorization inside its own scope so the optional built-in
+    // http-01 responder is spun up before the proofs are posted and torn down
+    // once all authorizations are valid, before the order is finalized.
+    {
+        // Optionally spin up the built-in http-01 responder for the whole order
+        // so that every authorization's proof can be served concurrently
+        // without an external hook. It is torn down when `_responder` is
+        // dropped at the end of this block.
+        #[cfg(feature = "http-01-responder")]
+        let _responder = match (&cert.challenge, cert.http01_addr) {
+            (Challenge::Http01, Some(addr)) => Some(responder::Http01Responder::bind(addr)?),
+            _ => None,
+        };
 
-        if auth.status == AuthorizationStatus::Valid {
-            continue;
-        }
-        if auth.status != AuthorizationStatus::Pending {
-            let msg = format!(
-                "{}: authorization status is {}",
-                auth.identifier, auth.status
-            );
-            return Err(msg.into());
-        }
+        // 5. Get all the required authorizations
+        for auth_url in order.authorizations.iter() {
+            let data_builder = set_empty_data_builder!(account, auth_url);
+            let (auth, new_nonce): (Authorization, String) =
+                http::get_obj(&auth_url, &data_builder, &nonce)?;
+            nonce = new_nonce;
 
-        // 6. For each authorization, fetch the associated challenges
-        for challenge in auth.challenges.iter() {
-            if cert.challenge == *challenge {
-                let proof = challenge.get_proof(&account.priv_key)?;
-                let file_name = challenge.get_file_name();
-                let domain = auth.identifier.value.to_owned();
-
-                // 7. Call the challenge hook in order to complete it
-                cert.call_challenge_hooks(&file_name, &proof, &domain)?;
-
-                // 8. Tell the server the challenge has been completed
-                let chall_url = challenge.get_url();
-                let data_builder = set_data_builder!(account, b"{}", chall_url);
-                let new_nonce = http::post_challenge_response(&chall_url, &data_builder, &nonce)?;
-                nonce = new_nonce;
+            if auth.status == AuthorizationStatus::Valid {
+                continue;
             }
-        }
+            if auth.status != AuthorizationStatus::Pending {
+                let msg = format!(
+                    "{}: authorization status is {}",
+                    auth.identifier, auth.status
+                );
+                return Err(msg.into());
+            }
+
+            // 6. For each authorization, fetch the associated challenges
+            for challenge in auth.challenges.iter() {
+                if cert.challenge == *challenge {
+                    let proof = challenge.get_proof(&account.priv_key)?;
+                    let file_name = challenge.get_file_name();
+                    let domain = auth.identifier.value.to_owned();
+
+                    // 7. Place the proof. When the built-in responder is active
+                    // it serves the proof directly; otherwise fall back to the
+                    // user-provided challenge hooks.
+                    #[cfg(feature = "http-01-responder")]
+                    if let Some(responder) = &_responder {
+                        responder.insert(&file_name, &proof);
+                    } else {
+                        cert.call_challenge_hooks(&file_name, &proof, &domain)?;
+                    }
+                    #[cfg(not(feature = "http-01-responder"))]
+                    cert.call_challenge_hooks(&file_name, &proof, &domain)?;
 
-        // 9. Pool the authorization in order to see whether or not it is valid
-        let data_builder = set_empty_data_builder!(account, auth_url);
-        let break_fn = |a: &Authorization| a.status == AuthorizationStatus::Valid;
-        let (_, new_nonce): (Authorization, String) =
-            http::pool_obj(&auth_url, &data_builder, &break_fn, &nonce)?;
-        nonce = new_nonce;
+                    // 8. Tell the server the challenge has been completed
+                    let chall_url = challenge.get_url();
+                    let data_builder = set_data_builder!(account, b"{}", chall_url);
+                    let new_nonce =
+                        http::post_challenge_response(&chall_url, &data_builder, &nonce)?;
+                    nonce = new_nonce;
+                }
+            }
+
+            // 9. Pool the authorization in order to see whether or not it is valid
+            let data_builder = set_empty_data_builder!(account, auth_url);
+            let break_fn = |a: &Authorization| a.status == AuthorizationStatus::Valid;
+            let (_, new_nonce): (Authorization, String) =
+                http::pool_obj(&auth_url, &data_builder, &break_fn, &nonce)?;
+            nonce = new_nonce;
+        }
     }
 
     // 10. Pool the order in order to see whether or not it is ready
@@ -157,6 +286,250 @@ pub fn request_certificate(cert: &Certificate) -> Result<(), Error> {
     Ok(())
 }
 
+pub fn request_revocation(cert: &Certificate, reason: RevocationReason) -> Result<(), Error> {
+    // 1. Get the directory
+    let directory = http::get_directory(&cert.remote_url)?;
+
+    // 2. Get a first nonce
+    let nonce = http::get_nonce(&directory.new_nonce)?;
+
+    // 3. Get or create the account
+    let (account, nonce) = AccountManager::new(cert, &directory, &nonce)?;
+
+    // 4. Build the revokeCert payload: the base64url DER of the certificate
+    //    together with the chosen reason code.
+    let der = certificate::get_certificate_der(cert)?;
+    let payload = serde_json::to_string(&RevokeCert {
+        certificate: b64_encode(&der),
+        reason: reason.code(),
+    })?;
+
+    // 5. POST it to the directory's revokeCert endpoint. The request may be
+    //    signed either with the account key (the usual case) or directly with
+    //    the certificate's own key pair, as allowed by RFC 8555.
+    let revoke_url = &directory.revoke_cert;
+    let result = if cert.revoke_with_cert_key {
+        let (priv_key, _) = certificate::get_key_pair(cert)?;
+        let data_builder =
+            |n: &str| encode_jwk(&priv_key, payload.as_bytes(), revoke_url, n);
+        http::post_challenge_response(revoke_url, &data_builder, &nonce)
+    } else {
+        let data_builder = set_data_builder!(account, payload.as_bytes(), revoke_url);
+        http::post_challenge_response(revoke_url, &data_builder, &nonce)
+    };
+
+    match result {
+        Ok(_) => {
+            info!("Certificate revoked for {}", cert.domains.join(", "));
+            Ok(())
+        }
+        // A certificate the server already considers revoked is not an error
+        // for our purposes: the desired end state has been reached.
+        Err(e) if e.to_string().contains("alreadyRevoked") => {
+            info!(
+                "Certificate for {} was already revoked",
+                cert.domains.join(", ")
+            );
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Compute a certificate's ARI identifier: the base64url-encoded Authority Key
+/// Identifier `keyIdentifier` and serial number of the issued certificate,
+/// joined by a `.` (RFC 9773 §4.1).
+fn ari_cert_id(cert: &Certificate) -> Result<String, Error> {
+    let (aki, serial) = certificate::ari_identifiers(cert)?;
+    Ok(format!("{}.{}", b64_encode(&aki), b64_encode(&serial)))
+}
+
+/// Number of days before a certificate's expiry within which it is renewed,
+/// used as a fallback when the CA does not publish ARI.
+const RENEWAL_THRESHOLD_DAYS: i64 = 30;
+
+/// Decide whether `cert` is due for renewal.
+///
+/// When the CA publishes ACME Renewal Information, the decision follows the
+/// suggested window: renewal happens once the current time has passed the
+/// randomized instant [`RenewalInfo::renewal_time`] picks inside
+/// `[start, end)`. Otherwise we fall back to the fixed threshold of renewing
+/// within [`RENEWAL_THRESHOLD_DAYS`] of the certificate's expiry.
+pub fn should_renew(cert: &Certificate) -> Result<bool, Error> {
+    if let Some(info) = request_renewal_info(cert)? {
+        return Ok(Utc::now() >= info.renewal_time());
+    }
+
+    let not_after = certificate::not_after(cert)?;
+    Ok(not_after - Utc::now() <= Duration::days(RENEWAL_THRESHOLD_DAYS))
+}
+
+/// Query the directory's `renewalInfo` resource for the suggested renewal
+/// window of the certificate. This is an unauthenticated GET, so no JWS is
+/// involved. Returns `None` when the CA does not advertise ARI.
+pub fn request_renewal_info(cert: &Certificate) -> Result<Option<RenewalInfo>, Error> {
+    let directory = http::get_directory(&cert.remote_url)?;
+    let renewal_info = match &directory.renewal_info {
+        Some(url) => url,
+        None => return Ok(None),
+    };
+
+    let url = format!("{}/{}", renewal_info.trim_end_matches('/'), ari_cert_id(cert)?);
+    let info: RenewalInfo = http::get_json(&url)?;
+    Ok(Some(info))
+}
+
+pub fn rollover_account_key(cert: &Certificate) -> Result<(), Error> {
+    // 1. Get the directory
+    let directory = http::get_directory(&cert.remote_url)?;
+
+    // 2. Get a first nonce
+    let nonce = http::get_nonce(&directory.new_nonce)?;
+
+    // 3. Get the account whose key is being rotated
+    let (mut account, nonce) = AccountManager::new(cert, &directory, &nonce)?;
+
+    // 4. Generate the key pair the account will switch to.
+    let new_key = account::new_key_pair()?;
+
+    // 5. Build the inner JWS, signed with the NEW key. Its protected header
+    //    carries the new public key as `jwk` and the `keyChange` URL, and has
+    //    no nonce; its payload binds the account URL to the old public key.
+    let inner_payload = serde_json::to_string(&KeyChange {
+        account: account.account_url.clone(),
+        old_key: jws::public_jwk(&account.priv_key)?,
+    })?;
+    let inner = jws::encode_jwk_no_nonce(&new_key, inner_payload.as_bytes(), &directory.key_change)?;
+
+    // 6. Wrap the inner object as the payload of an outer JWS signed with the
+    //    OLD key using `encode_kid`, and POST it to `keyChange`.
+    let data_builder = set_data_builder!(account, inner.as_bytes(), directory.key_change);
+    http::post_challenge_response(&directory.key_change, &data_builder, &nonce)?;
+
+    // 7. Persist the new key and swap it into the account manager.
+    storage::write_account_key(cert, &new_key)?;
+    account.priv_key = new_key;
+
+    info!("Rolled over account key for {}", cert.domains.join(", "));
+    Ok(())
+}
+
 pub fn b64_encode<T: ?Sized + AsRef<[u8]>>(input: &T) -> String {
     base64::encode_config(input, base64::URL_SAFE_NO_PAD)
 }
+
+/// A flattened JWS binding a freshly created account to an existing CA account,
+/// as mandated by CAs such as ZeroSSL, Sectigo or Google Trust Services
+/// (RFC 8555 §7.3.4). It is serialized into the `newAccount` request under the
+/// `externalAccountBinding` key by [`account::AccountManager::new`].
+#[derive(Clone, Debug, Serialize)]
+pub struct ExternalAccountBinding {
+    pub protected: String,
+    pub payload: String,
+    pub signature: String,
+}
+
+impl ExternalAccountBinding {
+    /// Compute the EAB inner JWS over the account's public JWK.
+    ///
+    /// `kid` and `hmac_key` come from the per-certificate `eab_kid` /
+    /// `eab_hmac_key` configuration; `hmac_key` is the base64url-encoded MAC
+    /// secret and `account_jwk` the serialized public JWK of the account key.
+    /// The protected header carries no `nonce`, as required for EAB.
+    pub fn new(
+        kid: &str,
+        hmac_key: &str,
+        account_jwk: &str,
+        new_account_url: &str,
+    ) -> Result<Self, Error> {
+        let protected = serde_json::json!({
+            "alg": "HS256",
+            "kid": kid,
+            "url": new_account_url,
+        });
+        let protected = b64_encode(&serde_json::to_string(&protected)?);
+        let payload = b64_encode(account_jwk);
+
+        let key = base64::decode_config(hmac_key, base64::URL_SAFE_NO_PAD)
+            .map_err(|e| Error::from(format!("eab: invalid hmac key: {}", e)))?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(&key)
+            .map_err(|e| Error::from(format!("eab: invalid hmac key: {}", e)))?;
+        mac.update(format!("{}.{}", protected, payload).as_bytes());
+        let signature = b64_encode(&mac.finalize().into_bytes());
+
+        Ok(ExternalAccountBinding {
+            protected,
+            payload,
+            signature,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn external_account_binding_known_answer() {
+        // HMAC-SHA256 vector computed independently from the inputs below.
+        let eab = ExternalAccountBinding::new(
+            "kid-1",
+            "c3VwZXJzZWNyZXQtbWFjLWtleQ",
+            "{\"kty\":\"oct\"}",
+            "https://example.test/acme/new-account",
+        )
+        .unwrap();
+        assert_eq!(
+            eab.protected,
+            "eyJhbGciOiJIUzI1NiIsImtpZCI6ImtpZC0xIiwidXJsIjoiaHR0cHM6Ly9leGFtcGxlLnRlc3QvYWNtZS9uZXctYWNjb3VudCJ9"
+        );
+        assert_eq!(eab.payload, "eyJrdHkiOiJvY3QifQ");
+        assert_eq!(eab.signature, "6Q5rQiS-UpTFSZwNyy_4xDJqo8iepX4Cbq17bO4qfhc");
+    }
+
+    #[test]
+    fn revocation_reason_round_trip() {
+        for reason in [
+            RevocationReason::Unspecified,
+            RevocationReason::KeyCompromise,
+            RevocationReason::AffiliationChanged,
+            RevocationReason::Superseded,
+            RevocationReason::CessationOfOperation,
+        ] {
+            let parsed = RevocationReason::from_str(&reason.to_string()).unwrap();
+            assert_eq!(parsed, reason);
+        }
+
+        assert_eq!(RevocationReason::Unspecified.code(), 0);
+        assert_eq!(RevocationReason::KeyCompromise.code(), 1);
+        assert_eq!(RevocationReason::AffiliationChanged.code(), 3);
+        assert_eq!(RevocationReason::Superseded.code(), 4);
+        assert_eq!(RevocationReason::CessationOfOperation.code(), 5);
+
+        assert!(RevocationReason::from_str("nope").is_err());
+    }
+
+    #[test]
+    fn renewal_time_within_window() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let end = DateTime::from_timestamp(1_700_003_600, 0).unwrap();
+        let info = RenewalInfo {
+            suggested_window: SuggestedWindow { start, end },
+            explanation_url: None,
+        };
+        for _ in 0..100 {
+            let t = info.renewal_time();
+            assert!(t >= start && t < end);
+        }
+    }
+
+    #[test]
+    fn renewal_time_empty_window() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let info = RenewalInfo {
+            suggested_window: SuggestedWindow { start, end: start },
+            explanation_url: None,
+        };
+        assert_eq!(info.renewal_time(), start);
+    }
+}
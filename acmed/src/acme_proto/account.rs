@@ -0,0 +1,85 @@
+use crate::acme_proto::jws::{encode_jwk, public_jwk};
+use crate::acme_proto::structs::{Account, Directory};
+use crate::acme_proto::{http, ExternalAccountBinding};
+use crate::certificate::Certificate;
+use crate::error::Error;
+use crate::storage;
+use openssl::ec::{EcGroup, EcKey};
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use serde::Serialize;
+
+pub struct AccountManager {
+    pub priv_key: PKey<Private>,
+    pub account_url: String,
+}
+
+/// Body of a `newAccount` request (RFC 8555 §7.3).
+#[derive(Serialize)]
+struct NewAccount {
+    #[serde(rename = "termsOfServiceAgreed")]
+    terms_of_service_agreed: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    contact: Vec<String>,
+    #[serde(
+        rename = "externalAccountBinding",
+        skip_serializing_if = "Option::is_none"
+    )]
+    external_account_binding: Option<ExternalAccountBinding>,
+}
+
+impl AccountManager {
+    pub fn new(
+        cert: &Certificate,
+        directory: &Directory,
+        nonce: &str,
+    ) -> Result<(Self, String), Error> {
+        // Load the persisted account key, generating and storing a fresh one
+        // the first time we register with this CA.
+        let priv_key = match storage::get_account_key(cert) {
+            Ok(key) => key,
+            Err(_) => {
+                let key = new_key_pair()?;
+                storage::write_account_key(cert, &key)?;
+                key
+            }
+        };
+
+        // When the CA mandates External Account Binding (ZeroSSL, Sectigo,
+        // Google Trust Services, …) and the certificate carries `eab_kid` /
+        // `eab_hmac_key`, compute the inner JWS over the account's public JWK
+        // and attach it to the newAccount request.
+        let external_account_binding = match (&cert.eab_kid, &cert.eab_hmac_key) {
+            (Some(kid), Some(hmac_key)) => {
+                let jwk = serde_json::to_string(&public_jwk(&priv_key)?)?;
+                Some(ExternalAccountBinding::new(
+                    kid,
+                    hmac_key,
+                    &jwk,
+                    &directory.new_account,
+                )?)
+            }
+            _ => None,
+        };
+
+        let payload = serde_json::to_string(&NewAccount {
+            terms_of_service_agreed: true,
+            contact: cert.contacts.clone(),
+            external_account_binding,
+        })?;
+
+        let data_builder =
+            |n: &str| encode_jwk(&priv_key, payload.as_bytes(), &directory.new_account, n);
+        let (_, account_url, nonce): (Account, String, String) =
+            http::get_obj_loc(&directory.new_account, &data_builder, nonce)?;
+
+        Ok((AccountManager { priv_key, account_url }, nonce))
+    }
+}
+
+/// Generate a fresh P-256 account key pair.
+pub fn new_key_pair() -> Result<PKey<Private>, Error> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+    let key = EcKey::generate(&group)?;
+    Ok(PKey::from_ec_key(key)?)
+}
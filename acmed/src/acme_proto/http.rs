@@ -0,0 +1,184 @@
+use crate::acme_proto::retry::Backoff;
+use crate::acme_proto::structs::Directory;
+use crate::error::Error;
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{HeaderValue, CONTENT_TYPE, LOCATION, RETRY_AFTER};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+const REPLAY_NONCE: &str = "Replay-Nonce";
+const JOSE_JSON: &str = "application/jose+json";
+
+/// Upper bound on the number of polls before a resource that never settles is
+/// failed with a timeout error.
+const MAX_POLL_ATTEMPTS: u32 = 15;
+
+fn client() -> Result<Client, Error> {
+    Client::builder()
+        .build()
+        .map_err(|e| Error::from(e.to_string()))
+}
+
+/// An ACME problem document (RFC 8555 §6.7), returned on error responses.
+#[derive(Debug, Default, Deserialize)]
+struct Problem {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    detail: Option<String>,
+}
+
+/// Turn a non-2xx response into an `Err` carrying the ACME error `type`, so
+/// callers can match on conditions such as `alreadyRevoked`. Successful
+/// responses are passed through untouched.
+fn check_status(resp: Response) -> Result<Response, Error> {
+    if resp.status().is_success() {
+        return Ok(resp);
+    }
+    let status = resp.status();
+    let problem: Problem = resp.json().unwrap_or_default();
+    let kind = problem.kind.unwrap_or_else(|| "unknown".to_owned());
+    let detail = problem.detail.unwrap_or_default();
+    Err(format!("{}: {} ({})", status, kind, detail).into())
+}
+
+fn extract_nonce(resp: &Response) -> Result<String, Error> {
+    resp.headers()
+        .get(REPLAY_NONCE)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_owned())
+        .ok_or_else(|| Error::from("missing Replay-Nonce header"))
+}
+
+fn post_jose<F>(url: &str, data_builder: &F, nonce: &str) -> Result<Response, Error>
+where
+    F: Fn(&str) -> Result<String, Error>,
+{
+    let body = data_builder(nonce)?;
+    let resp = client()?
+        .post(url)
+        .header(CONTENT_TYPE, HeaderValue::from_static(JOSE_JSON))
+        .body(body)
+        .send()
+        .map_err(|e| Error::from(e.to_string()))?;
+    check_status(resp)
+}
+
+pub fn get_directory(url: &str) -> Result<Directory, Error> {
+    client()?
+        .get(url)
+        .send()
+        .map_err(|e| Error::from(e.to_string()))?
+        .json()
+        .map_err(|e| Error::from(e.to_string()))
+}
+
+pub fn get_nonce(url: &str) -> Result<String, Error> {
+    let resp = client()?
+        .head(url)
+        .send()
+        .map_err(|e| Error::from(e.to_string()))?;
+    extract_nonce(&resp)
+}
+
+pub fn get_obj<T, F>(url: &str, data_builder: &F, nonce: &str) -> Result<(T, String), Error>
+where
+    T: DeserializeOwned,
+    F: Fn(&str) -> Result<String, Error>,
+{
+    let resp = post_jose(url, data_builder, nonce)?;
+    let nonce = extract_nonce(&resp)?;
+    let obj = resp.json::<T>().map_err(|e| Error::from(e.to_string()))?;
+    Ok((obj, nonce))
+}
+
+pub fn get_obj_loc<T, F>(
+    url: &str,
+    data_builder: &F,
+    nonce: &str,
+) -> Result<(T, String, String), Error>
+where
+    T: DeserializeOwned,
+    F: Fn(&str) -> Result<String, Error>,
+{
+    let resp = post_jose(url, data_builder, nonce)?;
+    let nonce = extract_nonce(&resp)?;
+    let location = resp
+        .headers()
+        .get(LOCATION)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_owned())
+        .ok_or_else(|| Error::from("missing Location header"))?;
+    let obj = resp.json::<T>().map_err(|e| Error::from(e.to_string()))?;
+    Ok((obj, location, nonce))
+}
+
+pub fn post_challenge_response<F>(url: &str, data_builder: &F, nonce: &str) -> Result<String, Error>
+where
+    F: Fn(&str) -> Result<String, Error>,
+{
+    let resp = post_jose(url, data_builder, nonce)?;
+    extract_nonce(&resp)
+}
+
+pub fn get_certificate<F>(
+    url: &str,
+    data_builder: &F,
+    nonce: &str,
+) -> Result<(String, String), Error>
+where
+    F: Fn(&str) -> Result<String, Error>,
+{
+    let resp = post_jose(url, data_builder, nonce)?;
+    let nonce = extract_nonce(&resp)?;
+    let crt = resp.text().map_err(|e| Error::from(e.to_string()))?;
+    Ok((crt, nonce))
+}
+
+/// Unauthenticated GET returning a deserialized JSON body (used by ARI).
+pub fn get_json<T: DeserializeOwned>(url: &str) -> Result<T, Error> {
+    client()?
+        .get(url)
+        .send()
+        .map_err(|e| Error::from(e.to_string()))?
+        .json()
+        .map_err(|e| Error::from(e.to_string()))
+}
+
+/// Poll `url` until `break_fn` holds, pacing requests per the server's pacing
+/// signals.
+///
+/// After each poll the response's `Retry-After` header is honored when present;
+/// otherwise a capped exponential backoff is applied. A bounded number of
+/// attempts ([`MAX_POLL_ATTEMPTS`]) ensures a stuck "pending" resource fails
+/// with a clear timeout error rather than spinning indefinitely against a
+/// rate-limited CA.
+pub fn pool_obj<T, F, B>(
+    url: &str,
+    data_builder: &F,
+    break_fn: &B,
+    nonce: &str,
+) -> Result<(T, String), Error>
+where
+    T: DeserializeOwned,
+    F: Fn(&str) -> Result<String, Error>,
+    B: Fn(&T) -> bool,
+{
+    let mut nonce = nonce.to_owned();
+    let mut backoff = Backoff::new(MAX_POLL_ATTEMPTS);
+    loop {
+        let resp = post_jose(url, data_builder, &nonce)?;
+        nonce = extract_nonce(&resp)?;
+        let retry_after = resp
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_owned());
+        let obj = resp.json::<T>().map_err(|e| Error::from(e.to_string()))?;
+
+        if break_fn(&obj) {
+            return Ok((obj, nonce));
+        }
+
+        backoff.wait(retry_after.as_deref())?;
+    }
+}
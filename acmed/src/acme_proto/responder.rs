@@ -0,0 +1,114 @@
+use crate::error::Error;
+use log::debug;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+/// Well-known path prefix under which http-01 proofs are served.
+const ACME_PREFIX: &str = "/.well-known/acme-challenge/";
+
+/// A minimal, self-contained http-01 challenge responder.
+///
+/// It binds a listener on the configured [`SocketAddr`] and answers
+/// `GET /.well-known/acme-challenge/<file_name>` with the matching proof and a
+/// `Content-Type: application/octet-stream`. Proofs are keyed on `file_name` so
+/// every authorization handled in the order loop can be served concurrently by
+/// a single responder, removing the need for an external hook in the common
+/// http-01 case. The listener is stopped and joined on [`Drop`].
+pub struct Http01Responder {
+    proofs: Arc<Mutex<HashMap<String, String>>>,
+    running: Arc<AtomicBool>,
+    listener_addr: SocketAddr,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Http01Responder {
+    /// Bind the responder on `addr` and start serving in a background thread.
+    pub fn bind(addr: SocketAddr) -> Result<Self, Error> {
+        let listener = TcpListener::bind(addr)
+            .map_err(|e| Error::from(format!("http-01 responder: bind {}: {}", addr, e)))?;
+        let listener_addr = listener.local_addr().unwrap_or(addr);
+        let proofs: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
+        let running = Arc::new(AtomicBool::new(true));
+
+        let thread_proofs = Arc::clone(&proofs);
+        let thread_running = Arc::clone(&running);
+        let handle = std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                if !thread_running.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    if let Err(e) = handle_connection(stream, &thread_proofs) {
+                        debug!("http-01 responder: {}", e);
+                    }
+                }
+            }
+        });
+
+        Ok(Http01Responder {
+            proofs,
+            running,
+            listener_addr,
+            handle: Some(handle),
+        })
+    }
+
+    /// Register the `proof` to serve for the given challenge `file_name`.
+    pub fn insert(&self, file_name: &str, proof: &str) {
+        self.proofs
+            .lock()
+            .unwrap()
+            .insert(file_name.to_owned(), proof.to_owned());
+    }
+}
+
+impl Drop for Http01Responder {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        // Unblock the accept loop with a throwaway connection so the thread can
+        // observe the stop flag and exit.
+        let _ = TcpStream::connect(self.listener_addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    proofs: &Arc<Mutex<HashMap<String, String>>>,
+) -> Result<(), Error> {
+    let mut buf = [0u8; 1024];
+    let read = stream
+        .read(&mut buf)
+        .map_err(|e| Error::from(format!("read request: {}", e)))?;
+    let request = String::from_utf8_lossy(&buf[..read]);
+
+    let path = request
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .unwrap_or("");
+
+    let response = match path.strip_prefix(ACME_PREFIX) {
+        Some(file_name) => match proofs.lock().unwrap().get(file_name) {
+            Some(proof) => format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                proof.len(),
+                proof
+            ),
+            None => "HTTP/1.1 404 Not Found\r\nConnection: close\r\n\r\n".to_owned(),
+        },
+        None => "HTTP/1.1 404 Not Found\r\nConnection: close\r\n\r\n".to_owned(),
+    };
+
+    stream
+        .write_all(response.as_bytes())
+        .map_err(|e| Error::from(format!("write response: {}", e)))?;
+    Ok(())
+}
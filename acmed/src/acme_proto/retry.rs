@@ -0,0 +1,95 @@
+use crate::error::Error;
+use std::time::{Duration, SystemTime};
+
+/// Pacing policy used by [`http::pool_obj`](super::http) while polling an
+/// authorization or order.
+///
+/// Each poll asks the policy how long to wait before the next attempt: if the
+/// server sent a `Retry-After` header its value is honored, otherwise a capped
+/// exponential backoff is used (starting at [`Backoff::BASE`], doubling up to
+/// [`Backoff::CEILING`]). The policy also bounds the total number of attempts so
+/// a stuck "pending" resource fails with a clear timeout rather than spinning
+/// forever against a rate-limited CA.
+pub struct Backoff {
+    attempt: u32,
+    max_attempts: u32,
+    current: Duration,
+}
+
+impl Backoff {
+    /// Initial delay used when no `Retry-After` header is present.
+    pub const BASE: Duration = Duration::from_secs(1);
+    /// Upper bound on the exponential backoff delay.
+    pub const CEILING: Duration = Duration::from_secs(60);
+
+    pub fn new(max_attempts: u32) -> Self {
+        Backoff {
+            attempt: 0,
+            max_attempts,
+            current: Self::BASE,
+        }
+    }
+
+    /// Sleep before the next poll, preferring the server's `Retry-After` hint.
+    ///
+    /// Returns an error once `max_attempts` has been exhausted so the caller
+    /// surfaces a timeout instead of looping indefinitely.
+    pub fn wait(&mut self, retry_after: Option<&str>) -> Result<(), Error> {
+        self.attempt += 1;
+        if self.attempt >= self.max_attempts {
+            return Err(format!(
+                "timed out after {} attempts waiting for the resource to settle",
+                self.max_attempts
+            )
+            .into());
+        }
+
+        let delay = match retry_after.and_then(parse_retry_after) {
+            Some(delay) => delay,
+            None => {
+                let delay = self.current;
+                self.current = (self.current * 2).min(Self::CEILING);
+                delay
+            }
+        };
+
+        std::thread::sleep(delay);
+        Ok(())
+    }
+}
+
+/// Parse a `Retry-After` header value, supporting both the integer-seconds and
+/// the HTTP-date forms (RFC 9110 §10.2.3).
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let when = httpdate::parse_http_date(value).ok()?;
+    when.duration_since(SystemTime::now()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_after_integer_seconds() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after("  5 "), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn retry_after_future_http_date() {
+        let delay = parse_retry_after("Wed, 21 Oct 2099 07:28:00 GMT");
+        assert!(delay.is_some());
+        assert!(delay.unwrap() > Duration::from_secs(0));
+    }
+
+    #[test]
+    fn retry_after_past_or_invalid() {
+        // A date in the past yields no delay to wait for.
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+        assert_eq!(parse_retry_after("not-a-date"), None);
+    }
+}